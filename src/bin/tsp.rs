@@ -1,3 +1,7 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+
 use clap::Parser;
 use rand::Rng;
 use rand_pcg::Mcg128Xsl64;
@@ -5,12 +9,65 @@ use rustc_hash::FxHashMap;
 
 #[derive(Parser)]
 struct Args {
-    #[clap(short)]
-    n: usize,
-    #[clap(short, long)]
-    size: f64,
+    /// Number of towns. Required unless `--input` is given, in which case
+    /// it's inferred from the input file.
+    #[clap(short, required_unless_present = "input")]
+    n: Option<usize>,
+    /// Side length of the square random towns are drawn from. Required
+    /// unless `--input` is given.
+    #[clap(short, long, required_unless_present = "input")]
+    size: Option<f64>,
     #[clap(long, default_value = "1")]
     seed: u128,
+    /// Read towns or a distance matrix from a file instead of generating
+    /// random Euclidean points. See `--format` for the expected layout.
+    #[clap(long)]
+    input: Option<String>,
+    /// Layout of `--input`: `coords` (TSPLIB `NODE_COORD_SECTION`-style
+    /// `x y` rows, Euclidean distance), `matrix` (TSPLIB
+    /// `EDGE_WEIGHT_SECTION`-style explicit N*N distance matrix), or `auto`,
+    /// which reads a square file wider than 3 columns as a matrix and
+    /// anything else (including a 2x2 or 3x3 matrix) as coords.
+    #[clap(long, default_value = "auto")]
+    format: String,
+    /// Treat the distance matrix as asymmetric, so a tour and its reverse
+    /// are distinct states: disables `tour_as_key`'s reverse-if-larger
+    /// canonicalization.
+    #[clap(long)]
+    asymmetric: bool,
+    /// Starting tour for a barrier search: either a `tour_as_key` value or a
+    /// comma-separated permutation, e.g. `2,1,3`. Requires `--to`.
+    #[clap(long, requires = "to")]
+    from: Option<String>,
+    /// Goal tour for a barrier search, in the same format as `--from`.
+    #[clap(long)]
+    to: Option<String>,
+    /// Compute only the exact optimum via Held-Karp, skipping full
+    /// enumeration of the landscape.
+    #[clap(long)]
+    optimum_only: bool,
+    /// Restrict enumeration to permutation-rank range `start..end`, for
+    /// resumable or distributed runs. Defaults to the full `0..(n-1)!` range.
+    /// A partial range only sees its own shard's neighbors, so basin/local-
+    /// minimum assignments computed from it are unreliable; pass `--raw`
+    /// alongside it and merge shards before flood-filling.
+    #[clap(long)]
+    range: Option<String>,
+    /// Dump every enumerated tour as `key length neighbor_key...` instead of
+    /// flood-filling basins, so multiple `--range` shards can be merged into
+    /// one landscape before basin/local-minimum analysis.
+    #[clap(long)]
+    raw: bool,
+    /// Sample the landscape with simulated annealing instead of enumerating
+    /// it, for a wall-clock budget given in milliseconds.
+    #[clap(long)]
+    time_limit_ms: Option<u64>,
+    #[clap(long, default_value = "10.0")]
+    initial_temperature: f64,
+    #[clap(long, default_value = "1e-3")]
+    final_temperature: f64,
+    #[clap(long, default_value = "0.999")]
+    cooling_factor: f64,
 }
 
 pub trait LexicalPermutation {
@@ -88,9 +145,13 @@ fn calc_tour_length(dist_mat: &[Vec<f64>], tour: &[usize]) -> f64 {
     sum.add(dist_mat[*tour.last().unwrap()][0]).value()
 }
 
-fn tour_as_key(tour: &[usize]) -> u64 {
+/// Canonical key for `tour`. For symmetric costs a tour and its reverse have
+/// the same length, so both are folded to the same key by always encoding
+/// from whichever end starts with the smaller town; set `symmetric` to
+/// `false` to keep them distinct, which is required for asymmetric costs.
+fn tour_as_key(tour: &[usize], symmetric: bool) -> u64 {
     let mut key = 0;
-    if tour[0] < *tour.last().unwrap() {
+    if !symmetric || tour[0] < *tour.last().unwrap() {
         for t in tour {
             key <<= 4;
             key += (*t - 1) as u64;
@@ -112,24 +173,559 @@ fn factorial(n: usize) -> usize {
     m
 }
 
+/// All 2-opt neighbors of `tour`: reverse `tour[i..=j]` for every `i < j`.
+fn two_opt_neighbor_keys(tour: &[usize], symmetric: bool) -> Vec<u64> {
+    let mut keys = Vec::with_capacity(tour.len() * (tour.len() - 1) / 2);
+    for i in 0..tour.len() {
+        for j in (i + 1)..tour.len() {
+            let mut neighbor = tour.to_vec();
+            neighbor[i..=j].reverse();
+            keys.push(tour_as_key(&neighbor, symmetric));
+        }
+    }
+    keys
+}
+
+/// Follows steepest descent from `key` to the local minimum it drains into,
+/// memoizing every key visited along the way.
+fn steepest_descent_minimum(
+    key: u64,
+    tour_length: &FxHashMap<u64, f64>,
+    neighbors: &FxHashMap<u64, Vec<u64>>,
+    cache: &mut FxHashMap<u64, u64>,
+) -> u64 {
+    if let Some(&minimum) = cache.get(&key) {
+        return minimum;
+    }
+    let energy = tour_length[&key];
+    // A neighbor may be missing from `tour_length` when only a partial
+    // `--range` was enumerated; such neighbors are simply not descendable.
+    let steepest = neighbors[&key]
+        .iter()
+        .copied()
+        .filter(|n| tour_length.get(n).is_some_and(|&e| e < energy))
+        .min_by(|a, b| tour_length[a].partial_cmp(&tour_length[b]).unwrap());
+    let minimum = match steepest {
+        Some(n) => steepest_descent_minimum(n, tour_length, neighbors, cache),
+        None => key,
+    };
+    cache.insert(key, minimum);
+    minimum
+}
+
+/// Resolves a `--from`/`--to` argument into a canonical `tour_as_key` value,
+/// accepting either a raw key or a comma-separated permutation.
+fn parse_tour_key(spec: &str, symmetric: bool) -> u64 {
+    if spec.contains(',') {
+        let tour: Vec<usize> = spec
+            .split(',')
+            .map(|t| t.trim().parse().expect("tour entries must be numbers"))
+            .collect();
+        tour_as_key(&tour, symmetric)
+    } else {
+        spec.parse()
+            .expect("expected a tour_as_key value or a comma-separated tour")
+    }
+}
+
+/// A node on the bottleneck-Dijkstra frontier, ordered so `BinaryHeap` pops
+/// the smallest `bottleneck` first.
+struct BarrierState {
+    bottleneck: f64,
+    key: u64,
+}
+
+impl PartialEq for BarrierState {
+    fn eq(&self, other: &Self) -> bool {
+        self.bottleneck == other.bottleneck
+    }
+}
+
+impl Eq for BarrierState {}
+
+impl PartialOrd for BarrierState {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BarrierState {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.bottleneck.total_cmp(&self.bottleneck)
+    }
+}
+
+/// Bottleneck-Dijkstra: finds the `source -> goal` path over the 2-opt
+/// neighbor graph that minimizes the *maximum* energy visited along the way,
+/// i.e. the barrier separating the two basins. Returns the barrier energy
+/// and the full sequence of tour keys on the optimal path.
+fn min_bottleneck_path(
+    source: u64,
+    goal: u64,
+    tour_length: &FxHashMap<u64, f64>,
+    neighbors: &FxHashMap<u64, Vec<u64>>,
+) -> Option<(f64, Vec<u64>)> {
+    let mut best: FxHashMap<u64, f64> = FxHashMap::default();
+    let mut prev: FxHashMap<u64, u64> = FxHashMap::default();
+    let mut heap = BinaryHeap::new();
+
+    best.insert(source, tour_length[&source]);
+    heap.push(BarrierState {
+        bottleneck: tour_length[&source],
+        key: source,
+    });
+
+    while let Some(BarrierState { bottleneck, key }) = heap.pop() {
+        if key == goal {
+            let mut path = vec![key];
+            while let Some(&p) = prev.get(path.last().unwrap()) {
+                path.push(p);
+            }
+            path.reverse();
+            return Some((bottleneck, path));
+        }
+        if bottleneck > best[&key] {
+            continue;
+        }
+        for &m in &neighbors[&key] {
+            let Some(&m_energy) = tour_length.get(&m) else {
+                continue;
+            };
+            let new_bottleneck = bottleneck.max(m_energy);
+            if new_bottleneck < *best.get(&m).unwrap_or(&f64::INFINITY) {
+                best.insert(m, new_bottleneck);
+                prev.insert(m, key);
+                heap.push(BarrierState {
+                    bottleneck: new_bottleneck,
+                    key: m,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Exact TSP optimum via the Held-Karp bitmask DP: `dp[s][j]` is the minimum
+/// cost of a path starting at town 0, visiting exactly the towns encoded by
+/// `s` (bit `k` means town `k + 1`), and ending at town `j + 1`. Runs in
+/// `O(2^n * n^2)` time and `O(2^n * n)` memory, far cheaper than enumerating
+/// all `n!` tours. Returns the optimal length and the visiting order of
+/// towns `1..n`.
+fn held_karp(dist_mat: &[Vec<f64>]) -> (f64, Vec<usize>) {
+    let n = dist_mat.len();
+    if n <= 1 {
+        return (0.0, Vec::new());
+    }
+    let m = n - 1;
+    let full = (1usize << m) - 1;
+    let mut dp = vec![vec![f64::INFINITY; m]; 1 << m];
+    let mut parent = vec![vec![usize::MAX; m]; 1 << m];
+    for j in 0..m {
+        dp[1 << j][j] = dist_mat[0][j + 1];
+    }
+    for s in 1..=full {
+        for j in 0..m {
+            if s & (1 << j) == 0 || dp[s][j].is_infinite() {
+                continue;
+            }
+            let cost = dp[s][j];
+            for k in 0..m {
+                if s & (1 << k) != 0 {
+                    continue;
+                }
+                let ns = s | (1 << k);
+                let new_cost = cost + dist_mat[j + 1][k + 1];
+                if new_cost < dp[ns][k] {
+                    dp[ns][k] = new_cost;
+                    parent[ns][k] = j;
+                }
+            }
+        }
+    }
+
+    let mut best_j = 0;
+    let mut best_cost = f64::INFINITY;
+    for j in 0..m {
+        let cost = dp[full][j] + dist_mat[j + 1][0];
+        if cost < best_cost {
+            best_cost = cost;
+            best_j = j;
+        }
+    }
+
+    let mut tour = Vec::with_capacity(m);
+    let mut s = full;
+    let mut j = best_j;
+    loop {
+        tour.push(j + 1);
+        let p = parent[s][j];
+        if p == usize::MAX {
+            break;
+        }
+        s &= !(1 << j);
+        j = p;
+    }
+    tour.reverse();
+    (best_cost, tour)
+}
+
+/// Maps a permutation rank `r` in `[0, towns.len()!)` to its tour via the
+/// factorial number system: repeatedly divide by successive factorials to
+/// get Lehmer-code digits, then pick-and-remove from the remaining towns.
+fn unrank_tour(mut rank: usize, towns: &[usize]) -> Vec<usize> {
+    let mut remaining = towns.to_vec();
+    let mut tour = Vec::with_capacity(remaining.len());
+    for i in (1..=remaining.len()).rev() {
+        let f = factorial(i - 1);
+        let idx = rank / f;
+        rank %= f;
+        tour.push(remaining.remove(idx));
+    }
+    tour
+}
+
+/// Parses a `--range start..end` spec, clamping `end` to `total` and
+/// defaulting an empty `end` (`start..`) to `total`.
+fn parse_range(spec: &str, total: usize) -> (usize, usize) {
+    let (start, end) = spec
+        .split_once("..")
+        .expect("range must be of the form `start..end`");
+    let start: usize = start.trim().parse().expect("invalid range start");
+    let end: usize = if end.trim().is_empty() {
+        total
+    } else {
+        end.trim().parse().expect("invalid range end")
+    };
+    let (start, end) = (start.min(total), end.min(total));
+    assert!(start <= end, "range start ({start}) must be <= end ({end})");
+    (start, end)
+}
+
+/// Enumerates tour ranks `[start, end)` on the current thread, starting from
+/// the unranked permutation at `start` and walking forward with
+/// `next_permutation`.
+fn enumerate_range(
+    dist_mat: &[Vec<f64>],
+    towns: &[usize],
+    start: usize,
+    end: usize,
+    symmetric: bool,
+) -> (FxHashMap<u64, f64>, FxHashMap<u64, Vec<u64>>) {
+    let mut tour = unrank_tour(start, towns);
+    let mut tour_length = FxHashMap::default();
+    let mut neighbors = FxHashMap::default();
+    for _ in start..end {
+        let length = calc_tour_length(dist_mat, &tour);
+        let key = tour_as_key(&tour, symmetric);
+        tour_length.insert(key, length);
+        neighbors.insert(key, two_opt_neighbor_keys(&tour, symmetric));
+        tour.next_permutation();
+    }
+    (tour_length, neighbors)
+}
+
+/// Parses whitespace-separated rows of numbers out of `content`, skipping
+/// TSPLIB header/section lines (`NAME:`, `NODE_COORD_SECTION`, `EOF`, ...) by
+/// keeping only lines that start with a digit, `-`, or `.`.
+fn parse_numeric_rows(content: &str) -> Vec<Vec<f64>> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.'))
+        .map(|line| {
+            line.split_whitespace()
+                .map(|t| t.parse().expect("expected a number in --input"))
+                .collect()
+        })
+        .collect()
+}
+
+/// Reads towns or a distance matrix from `--input`. `format` is `coords`
+/// (TSPLIB `NODE_COORD_SECTION`-style `[index] x y` rows, turned into
+/// Euclidean distances via `make_dist_mat`), `matrix` (TSPLIB
+/// `EDGE_WEIGHT_SECTION`-style explicit N*N distance matrix), or `auto`,
+/// which picks `matrix` only for rows wider than 3 columns (coords rows are
+/// always `x y` or `index x y`, so at or below that width `auto` always
+/// reads coords — pass an explicit `--format` for a 2x2 or 3x3 matrix).
+///
+/// `symmetric` is `!args.asymmetric`: a `matrix` input is asserted
+/// symmetric unless `--asymmetric` was passed, since `tour_as_key` folds a
+/// tour and its reverse onto the same key and would otherwise silently
+/// drop one direction's true cost.
+fn read_input(path: &str, format: &str, symmetric: bool) -> Vec<Vec<f64>> {
+    let content = std::fs::read_to_string(path).expect("failed to read --input file");
+    let rows = parse_numeric_rows(&content);
+    let width = rows.first().map_or(0, Vec::len);
+    let is_matrix = match format {
+        "matrix" => true,
+        "coords" => false,
+        "auto" => width > 3 && rows.iter().all(|r| r.len() == rows.len()),
+        other => panic!("unknown --format {other:?}, expected coords, matrix, or auto"),
+    };
+    if is_matrix {
+        let n = rows.len();
+        assert!(
+            rows.iter().all(|r| r.len() == n),
+            "--format matrix requires a square N*N matrix, got a ragged input"
+        );
+        if symmetric {
+            assert!(
+                (0..n).all(|i| (0..n).all(|j| rows[i][j] == rows[j][i])),
+                "--input matrix is asymmetric; pass --asymmetric or tour/reverse energies will be silently folded together"
+            );
+        }
+        rows
+    } else {
+        assert!(
+            rows.iter().all(|r| r.len() >= 2),
+            "--format coords requires at least 2 columns (x y) per row"
+        );
+        let coords: Vec<[f64; 2]> = rows
+            .into_iter()
+            .map(|r| [r[r.len() - 2], r[r.len() - 1]])
+            .collect();
+        make_dist_mat(&coords)
+    }
+}
+
+/// A uniformly random tour over towns `1..n`, via Fisher-Yates shuffle.
+fn random_tour(rng: &mut Mcg128Xsl64, n: usize) -> Vec<usize> {
+    let mut tour: Vec<usize> = (1..n).collect();
+    for i in (1..tour.len()).rev() {
+        let j = rng.gen_range(0..=i);
+        tour.swap(i, j);
+    }
+    tour
+}
+
+/// The change in tour length from reversing `tour[i..=j]`, without
+/// recomputing the whole tour: only the two edges straddling the reversed
+/// segment change, since the segment's internal edges keep the same length.
+/// Only valid for symmetric costs — reversing a segment also flips the
+/// direction of every edge inside it, which changes their cost when
+/// `dist_mat` is asymmetric.
+fn two_opt_delta(dist_mat: &[Vec<f64>], tour: &[usize], i: usize, j: usize) -> f64 {
+    let before = if i == 0 { 0 } else { tour[i - 1] };
+    let after = if j + 1 == tour.len() { 0 } else { tour[j + 1] };
+    let old = dist_mat[before][tour[i]] + dist_mat[tour[j]][after];
+    let new = dist_mat[before][tour[j]] + dist_mat[tour[i]][after];
+    new - old
+}
+
+/// Initial/final temperature and the geometric cooling factor for
+/// `simulated_annealing`.
+struct CoolingSchedule {
+    initial_temperature: f64,
+    final_temperature: f64,
+    cooling_factor: f64,
+}
+
+/// Metropolis/simulated-annealing sampler: starting from a random tour,
+/// repeatedly proposes a 2-opt move and accepts it with probability
+/// `min(1, exp(-delta/T))` under a geometric cooling schedule `T <- T * alpha`,
+/// for a wall-clock budget. Every tour visited is recorded with its energy
+/// and visit count, approximating the reachable low-energy portion of the
+/// landscape for `n` too large to enumerate.
+fn simulated_annealing(
+    dist_mat: &[Vec<f64>],
+    rng: &mut Mcg128Xsl64,
+    n: usize,
+    time_limit_ms: u64,
+    schedule: &CoolingSchedule,
+    symmetric: bool,
+) -> (FxHashMap<u64, f64>, FxHashMap<u64, usize>) {
+    let mut tour = random_tour(rng, n);
+    let mut length = calc_tour_length(dist_mat, &tour);
+    let mut temperature = schedule.initial_temperature;
+
+    let mut tour_length = FxHashMap::default();
+    let mut visit_count: FxHashMap<u64, usize> = FxHashMap::default();
+    let deadline = Instant::now() + Duration::from_millis(time_limit_ms);
+
+    while Instant::now() < deadline {
+        let a = rng.gen_range(0..tour.len());
+        let b = rng.gen_range(0..tour.len());
+        let (i, j) = if a < b { (a, b) } else { (b, a) };
+        if i == j {
+            continue;
+        }
+
+        let delta = if symmetric {
+            two_opt_delta(dist_mat, &tour, i, j)
+        } else {
+            // The incremental shortcut assumes reversed edges keep their
+            // cost, which only holds for symmetric costs; recompute in full
+            // otherwise.
+            let mut candidate = tour.clone();
+            candidate[i..=j].reverse();
+            calc_tour_length(dist_mat, &candidate) - length
+        };
+        let accept = delta <= 0.0 || rng.gen_range(0.0..1.0) < (-delta / temperature).exp();
+        if accept {
+            tour[i..=j].reverse();
+            length += delta;
+        }
+
+        let key = tour_as_key(&tour, symmetric);
+        tour_length.insert(key, length);
+        *visit_count.entry(key).or_insert(0) += 1;
+        temperature = (temperature * schedule.cooling_factor).max(schedule.final_temperature);
+    }
+    (tour_length, visit_count)
+}
+
 fn main() {
     let args = Args::parse();
     let mut rng = Mcg128Xsl64::new(args.seed);
-    let towns = make_town(&mut rng, args.n, args.size);
-    let dist_mat = make_dist_mat(&towns);
-    let mut tour = (1..args.n).collect::<Vec<_>>();
-    let mut tour_length =
-        FxHashMap::with_capacity_and_hasher(factorial(args.n), Default::default());
+    let symmetric = !args.asymmetric;
+
+    let dist_mat = match &args.input {
+        Some(path) => read_input(path, &args.format, symmetric),
+        None => {
+            // clap's `required_unless_present` guarantees these are set here.
+            let n = args.n.expect("-n is required unless --input is given");
+            let size = args
+                .size
+                .expect("--size is required unless --input is given");
+            let towns = make_town(&mut rng, n, size);
+            make_dist_mat(&towns)
+        }
+    };
+    let n = dist_mat.len();
+
+    if args.optimum_only {
+        let (length, path) = held_karp(&dist_mat);
+        print!("0");
+        for t in &path {
+            print!(" {}", t);
+        }
+        println!();
+        println!("length {}", length);
+        return;
+    }
+
+    // tour_as_key packs each town into 4 bits, so it only has room for towns
+    // 1..=16 (n <= 17); held_karp above doesn't use tour_as_key and isn't
+    // bound by this.
+    assert!(
+        n <= 17,
+        "tour_as_key only supports up to 17 towns (n = {n}); use --optimum-only for larger instances"
+    );
+
+    if let Some(time_limit_ms) = args.time_limit_ms {
+        let schedule = CoolingSchedule {
+            initial_temperature: args.initial_temperature,
+            final_temperature: args.final_temperature,
+            cooling_factor: args.cooling_factor,
+        };
+        let (tour_length, visit_count) =
+            simulated_annealing(&dist_mat, &mut rng, n, time_limit_ms, &schedule, symmetric);
+        let mut min_length = f64::INFINITY;
+        for &length in tour_length.values() {
+            min_length = min_length.min(length);
+        }
+        for (key, length) in &tour_length {
+            println!("{} {} {}", key, length - min_length, visit_count[key]);
+        }
+        return;
+    }
+
+    let town_seq = (1..n).collect::<Vec<_>>();
+    let total = factorial(town_seq.len());
+    let (range_start, range_end) = match &args.range {
+        Some(spec) => parse_range(spec, total),
+        None => (0, total),
+    };
+
+    let num_threads = std::thread::available_parallelism()
+        .map(|p| p.get())
+        .unwrap_or(1)
+        .min((range_end - range_start).max(1));
+    let chunk_size = (range_end - range_start).div_ceil(num_threads);
+
+    let mut tour_length = FxHashMap::default();
+    let mut neighbors = FxHashMap::default();
+    std::thread::scope(|scope| {
+        let mut handles = Vec::new();
+        let mut start = range_start;
+        while start < range_end {
+            let end = (start + chunk_size).min(range_end);
+            let town_seq = &town_seq;
+            let dist_mat = &dist_mat;
+            handles.push(
+                scope.spawn(move || enumerate_range(dist_mat, town_seq, start, end, symmetric)),
+            );
+            start = end;
+        }
+        for handle in handles {
+            let (thread_tour_length, thread_neighbors) = handle.join().unwrap();
+            tour_length.extend(thread_tour_length);
+            neighbors.extend(thread_neighbors);
+        }
+    });
+
     let mut min_length = f64::INFINITY;
-    loop {
-        let length = calc_tour_length(&dist_mat, &tour);
-        tour_length.insert(tour_as_key(&tour), length);
+    for &length in tour_length.values() {
         min_length = min_length.min(length);
-        if !tour.next_permutation() {
-            break;
+    }
+
+    if args.raw {
+        for (key, length) in &tour_length {
+            let neighbor_keys = neighbors
+                .get(key)
+                .map(|ks| {
+                    ks.iter()
+                        .map(|k| k.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .unwrap_or_default();
+            println!("{} {} {}", key, length, neighbor_keys);
+        }
+        return;
+    }
+
+    if let (Some(from), Some(to)) = (&args.from, &args.to) {
+        let source = parse_tour_key(from, symmetric);
+        let goal = parse_tour_key(to, symmetric);
+        if !tour_length.contains_key(&source) || !tour_length.contains_key(&goal) {
+            println!("--from/--to tour was not enumerated in this --range");
+            return;
+        }
+        match min_bottleneck_path(source, goal, &tour_length, &neighbors) {
+            Some((bottleneck, path)) => {
+                println!("barrier {}", bottleneck - min_length);
+                for key in path {
+                    println!("{} {}", key, tour_length[&key] - min_length);
+                }
+            }
+            None => println!("no path found between {} and {}", source, goal),
         }
+        return;
     }
-    for (key, length) in tour_length.iter() {
-        println!("{} {}", key, *length - min_length);
+
+    if args.range.is_some() {
+        eprintln!(
+            "warning: basin/local-minimum assignments from a partial --range are unreliable, \
+             since tours whose true downhill neighbor falls outside this shard get misattributed; \
+             pass --raw and merge shards before flood-filling for a correct landscape"
+        );
+    }
+
+    // Flood-fill every tour into the basin of the local minimum it descends to.
+    let mut descent_cache = FxHashMap::default();
+    let mut basin_size: FxHashMap<u64, usize> = FxHashMap::default();
+    for &key in tour_length.keys() {
+        let minimum = steepest_descent_minimum(key, &tour_length, &neighbors, &mut descent_cache);
+        *basin_size.entry(minimum).or_insert(0) += 1;
+    }
+
+    for (minimum, size) in basin_size {
+        println!(
+            "{} {} {}",
+            minimum,
+            tour_length[&minimum] - min_length,
+            size
+        );
     }
 }